@@ -1,24 +1,150 @@
 use std::{
-    collections::{BTreeMap, BTreeSet},
+    collections::BTreeMap,
     io,
+    sync::{mpsc, Mutex},
+    thread,
 };
 
 use super::Result;
 use rust_decimal::Decimal;
 use serde::Serialize;
+use thiserror::Error;
 
 use crate::transaction;
 
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum LedgerError {
+    #[error("unknown transaction {1} for client {0}")]
+    UnknownTx(u16, u32),
+    #[error("transaction {0} is already disputed")]
+    AlreadyDisputed(u32),
+    #[error("transaction {0} is not currently disputed")]
+    NotDisputed(u32),
+    #[error("account for client {0} is frozen")]
+    FrozenAccount(u16),
+    #[error("client {0} has insufficient available funds")]
+    InsufficientFunds(u16),
+    #[error("total issuance for asset {0} is inconsistent: expected {1}, found {2}")]
+    IssuanceMismatch(String, Decimal, Decimal),
+    #[error("client {0} has no reserve named {1}")]
+    UnknownReserve(u16, ReserveId),
+    #[error("client {0}'s reserve {1} does not hold {2}")]
+    InsufficientReserve(u16, ReserveId, Decimal),
+}
+
+/// Identifies a named hold on an [`Account`]'s available balance. The dispute lifecycle keys its
+/// reserve by the disputed transaction's id, but callers are free to reserve funds under any id.
+pub type ReserveId = u32;
+
+/// The lifecycle of a disputable transaction, keyed by its tx id in `Bank::transactions`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TxState {
+    Processed,
+    Disputed,
+    Resolved,
+    ChargedBack,
+}
+
+/// Whether a recorded transaction was a deposit or a withdrawal, since disputing one reverses
+/// the opposite side of the client's balance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TxKind {
+    Deposit,
+    Withdrawal,
+}
+
+/// A deposit or withdrawal kept around so a later dispute/resolve/chargeback can find the
+/// original client, asset, amount and kind it applies to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RecordedTx {
+    client: u16,
+    asset: String,
+    amount: Decimal,
+    kind: TxKind,
+    state: TxState,
+}
+
+/// Uniquely identifies a client's balance in a single asset.
+type AccountKey = (u16, String);
+
 #[derive(Default, Debug, PartialEq, Eq, Clone)]
 struct Account {
     available: Decimal,
-    held: Decimal,
+    /// Named holds on `available`, e.g. one per disputed transaction. Overlay the same balance
+    /// rather than stacking beyond it: a reserve can never push `available` below zero.
+    reserves: BTreeMap<ReserveId, Decimal>,
     locked: bool,
 }
 
+impl Account {
+    fn reserved(&self) -> Decimal {
+        self.reserves.values().copied().sum()
+    }
+
+    fn total(&self) -> Decimal {
+        self.available + self.reserved()
+    }
+
+    /// Moves `amount` out of `available` into the reserve named `id`, failing instead of letting
+    /// `available` go negative.
+    fn reserve(&mut self, client: u16, id: ReserveId, amount: Decimal) -> Result<(), LedgerError> {
+        if amount > self.available {
+            return Err(LedgerError::InsufficientFunds(client));
+        }
+        self.available -= amount;
+        *self.reserves.entry(id).or_default() += amount;
+        Ok(())
+    }
+
+    /// Adds `amount` to the reserve named `id` without taking it out of `available`, for funds
+    /// that are already outside the client's available balance (e.g. a disputed withdrawal).
+    fn hold(&mut self, id: ReserveId, amount: Decimal) {
+        *self.reserves.entry(id).or_default() += amount;
+    }
+
+    /// Moves `amount` back out of the reserve named `id` into `available`.
+    fn unreserve(&mut self, client: u16, id: ReserveId, amount: Decimal) -> Result<(), LedgerError> {
+        self.take_from_reserve(client, id, amount)?;
+        self.available += amount;
+        Ok(())
+    }
+
+    /// Permanently removes `amount` from the reserve named `id` without returning it to
+    /// `available`, e.g. for a chargeback.
+    fn slash_reserved(
+        &mut self,
+        client: u16,
+        id: ReserveId,
+        amount: Decimal,
+    ) -> Result<(), LedgerError> {
+        self.take_from_reserve(client, id, amount)
+    }
+
+    fn take_from_reserve(
+        &mut self,
+        client: u16,
+        id: ReserveId,
+        amount: Decimal,
+    ) -> Result<(), LedgerError> {
+        let reserved = self
+            .reserves
+            .get_mut(&id)
+            .ok_or(LedgerError::UnknownReserve(client, id))?;
+        if amount > *reserved {
+            return Err(LedgerError::InsufficientReserve(client, id, amount));
+        }
+        *reserved -= amount;
+        if reserved.is_zero() {
+            self.reserves.remove(&id);
+        }
+        Ok(())
+    }
+}
+
 #[derive(Serialize)]
 pub struct SerializableAccount {
     client: u16,
+    asset: String,
     available: String,
     held: String,
     total: String,
@@ -26,12 +152,16 @@ pub struct SerializableAccount {
 }
 
 impl SerializableAccount {
-    fn from_account(client: u16, account: &Account) -> Self {
+    fn from_account(client: u16, asset: &str, account: &Account) -> Self {
         Self {
             client,
-            available: account.available.round_dp(4).to_string(),
-            held: account.held.round_dp(4).to_string(),
-            total: (account.available.round_dp(4) + account.held.round_dp(4)).to_string(),
+            asset: asset.to_owned(),
+            // `round_dp` only caps the number of decimals, it doesn't strip trailing zeros, so a
+            // value built up from e.g. `dec!(10.0) - dec!(4.0)` would otherwise print "6.0"
+            // instead of "6". Normalize to the canonical scale regardless of how it was derived.
+            available: account.available.round_dp(4).normalize().to_string(),
+            held: account.reserved().round_dp(4).normalize().to_string(),
+            total: account.total().round_dp(4).normalize().to_string(),
             locked: account.locked,
         }
     }
@@ -39,102 +169,496 @@ impl SerializableAccount {
 
 #[derive(Debug, Default, PartialEq, Eq, Clone)]
 pub struct Bank {
-    /// Map from client id to account details
-    accounts: BTreeMap<u16, Account>,
-    /// Map from transaction id to deposit transaction data
-    deposits: BTreeMap<u32, transaction::TransactionAmountData>,
-    /// Set of transaction id of disputed deposits
-    disputes: BTreeSet<u32>,
+    /// Map from (client id, asset) to account details
+    accounts: BTreeMap<AccountKey, Account>,
+    /// Map from transaction id to the deposit/withdrawal it originated from, and its current
+    /// dispute lifecycle state
+    transactions: BTreeMap<u32, RecordedTx>,
+    /// Map from asset to the net amount of that asset deposits, withdrawals and chargebacks have
+    /// added to or removed from the bank. Disputing a withdrawal adds its amount back while the
+    /// dispute is open, and resolving it removes that amount again. Transfers move funds between
+    /// clients without changing this, so [`Bank::audit`] can check it against the sum of all
+    /// account balances.
+    total_issuance: BTreeMap<String, Decimal>,
 }
 
 impl Bank {
-    /// Update the internal state of the bank according to the given transaction
-    pub fn handle_transaction(&mut self, transaction: transaction::Transaction) {
-        // Get the client's account
-        let mut account = self.accounts.entry(transaction.client_id()).or_default();
-
-        // Check that the account isn't locked
-        if account.locked {
-            return;
-        }
-
+    /// Update the internal state of the bank according to the given transaction.
+    ///
+    /// Returns an error describing why the transaction was rejected (unknown tx, a dispute
+    /// transition that doesn't apply, a frozen account, or insufficient available funds) instead
+    /// of silently ignoring it.
+    pub fn handle_transaction(
+        &mut self,
+        transaction: transaction::Transaction,
+    ) -> Result<(), LedgerError> {
         match transaction {
             transaction::Transaction::Deposit(data) => {
+                let account = self
+                    .accounts
+                    .entry((data.client, data.asset.clone()))
+                    .or_default();
+                if account.locked {
+                    return Err(LedgerError::FrozenAccount(data.client));
+                }
+
                 account.available += data.amount;
-                // deposit into the available funds
-                self.deposits.insert(data.tx, data);
+                *self.total_issuance.entry(data.asset.clone()).or_default() += data.amount;
+                self.transactions.insert(
+                    data.tx,
+                    RecordedTx {
+                        client: data.client,
+                        asset: data.asset,
+                        amount: data.amount,
+                        kind: TxKind::Deposit,
+                        state: TxState::Processed,
+                    },
+                );
             }
             transaction::Transaction::Withdrawal(data) => {
-                // Only apply withdraw if the account has enough available funds
-                if data.amount <= account.available {
-                    // Withdraw the funds
-                    account.available -= data.amount
+                let account = self
+                    .accounts
+                    .entry((data.client, data.asset.clone()))
+                    .or_default();
+                if account.locked {
+                    return Err(LedgerError::FrozenAccount(data.client));
+                }
+                if data.amount > account.available {
+                    return Err(LedgerError::InsufficientFunds(data.client));
                 }
+
+                account.available -= data.amount;
+                *self.total_issuance.entry(data.asset.clone()).or_default() -= data.amount;
+                self.transactions.insert(
+                    data.tx,
+                    RecordedTx {
+                        client: data.client,
+                        asset: data.asset,
+                        amount: data.amount,
+                        kind: TxKind::Withdrawal,
+                        state: TxState::Processed,
+                    },
+                );
             }
             transaction::Transaction::Dispute(data) => {
-                // Get the disputed deposit, ignore if it doesn't exist
-                if let Some(disputed_deposit) = self.deposits.get(&data.tx) {
-                    // Check that the dispute client id is the same as the deposit client id,
-                    // otherwise ignore
-                    if data.client == disputed_deposit.client {
-                        // Move the deposit into the held funds
-                        account.available -= disputed_deposit.amount;
-                        account.held += disputed_deposit.amount;
-
-                        self.disputes.insert(data.tx);
+                let recorded = self
+                    .transactions
+                    .get(&data.tx)
+                    .filter(|recorded| recorded.client == data.client)
+                    .ok_or(LedgerError::UnknownTx(data.client, data.tx))?;
+
+                if recorded.state != TxState::Processed {
+                    return Err(LedgerError::AlreadyDisputed(data.tx));
+                }
+                let (asset, amount, kind) =
+                    (recorded.asset.clone(), recorded.amount, recorded.kind);
+
+                let account = self
+                    .accounts
+                    .entry((data.client, asset.clone()))
+                    .or_default();
+                if account.locked {
+                    return Err(LedgerError::FrozenAccount(data.client));
+                }
+
+                match kind {
+                    // The deposited funds are no longer available to spend while disputed; reserve
+                    // them under the disputed tx's id.
+                    TxKind::Deposit => account.reserve(data.client, data.tx, amount)?,
+                    // The withdrawn funds already left `available`, so just hold them against the
+                    // chance the withdrawal is charged back, without debiting `available` again.
+                    // Those funds already left `total_issuance` when the withdrawal was processed,
+                    // so re-fund the hold here or `Bank::audit` would see the reserve as
+                    // unbacked until the dispute resolves.
+                    TxKind::Withdrawal => {
+                        account.hold(data.tx, amount);
+                        *self.total_issuance.entry(asset).or_default() += amount;
                     }
                 }
+
+                self.transactions.get_mut(&data.tx).unwrap().state = TxState::Disputed;
             }
             transaction::Transaction::Resolve(data) => {
-                // Check that the transaction is actually under dispute, ignore if it is not
-                if self.disputes.remove(&data.tx) {
-                    // Get the disputed deposit, ignore if it doesn't exist
-                    if let Some(disputed_deposit) = self.deposits.get(&data.tx) {
-                        // Check that the dispute client id is the same as the resolve client id,
-                        // otherwise ignore
-                        if data.client == disputed_deposit.client {
-                            // Reverse the dispute
-                            account.held -= disputed_deposit.amount;
-                            account.available += disputed_deposit.amount;
-                        }
+                let recorded = self
+                    .transactions
+                    .get(&data.tx)
+                    .filter(|recorded| recorded.client == data.client)
+                    .ok_or(LedgerError::UnknownTx(data.client, data.tx))?;
+
+                if recorded.state != TxState::Disputed {
+                    return Err(LedgerError::NotDisputed(data.tx));
+                }
+                let (asset, amount, kind) =
+                    (recorded.asset.clone(), recorded.amount, recorded.kind);
+
+                let account = self
+                    .accounts
+                    .entry((data.client, asset.clone()))
+                    .or_default();
+                match kind {
+                    // The dispute was rejected, return the deposit to available funds.
+                    TxKind::Deposit => account.unreserve(data.client, data.tx, amount)?,
+                    // The dispute was rejected, the withdrawal stands: drop the hold without
+                    // crediting `available`, and undo the hold's dispute-time refund now that the
+                    // funds have left the bank for good again.
+                    TxKind::Withdrawal => {
+                        account.slash_reserved(data.client, data.tx, amount)?;
+                        *self.total_issuance.entry(asset).or_default() -= amount;
                     }
                 }
+
+                self.transactions.get_mut(&data.tx).unwrap().state = TxState::Resolved;
             }
             transaction::Transaction::Chargeback(data) => {
-                // Check that the transaction is actually under dispute, ignore if it is not
-                if self.disputes.remove(&data.tx) {
-                    // Get the disputed deposit, ignore if it doesn't exist
-                    if let Some(disputed_deposit) = self.deposits.get(&data.tx) {
-                        // Check that the dispute client id is the same as the chargeback client id,
-                        // otherwise ignore
-                        if data.client == disputed_deposit.client {
-                            // Reverse the deposit
-                            account.held -= disputed_deposit.amount;
-                            account.locked = true;
-                        }
+                let recorded = self
+                    .transactions
+                    .get(&data.tx)
+                    .filter(|recorded| recorded.client == data.client)
+                    .ok_or(LedgerError::UnknownTx(data.client, data.tx))?;
+
+                if recorded.state != TxState::Disputed {
+                    return Err(LedgerError::NotDisputed(data.tx));
+                }
+                let (asset, amount, kind) =
+                    (recorded.asset.clone(), recorded.amount, recorded.kind);
+
+                let account = self.accounts.entry((data.client, asset.clone())).or_default();
+                match kind {
+                    // Reverse the deposit entirely: the funds never really left the bank.
+                    TxKind::Deposit => {
+                        account.slash_reserved(data.client, data.tx, amount)?;
+                        *self.total_issuance.entry(asset).or_default() -= amount;
+                    }
+                    // Reverse the withdrawal by crediting the funds back. The dispute already
+                    // re-funded this amount into `total_issuance`, so moving it from reserved to
+                    // available here is issuance-neutral, like a transfer.
+                    TxKind::Withdrawal => {
+                        account.unreserve(data.client, data.tx, amount)?;
                     }
                 }
+                account.locked = true;
+
+                self.transactions.get_mut(&data.tx).unwrap().state = TxState::ChargedBack;
             }
+            transaction::Transaction::Transfer(data) => {
+                self.can_debit(data.client, &data.asset, data.amount)?;
+                self.can_credit(data.to_client, &data.asset)?;
+
+                self.debit(data.client, &data.asset, data.amount);
+                self.credit(data.to_client, &data.asset, data.amount);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks whether `amount` could be debited from `client`'s `asset` account, without
+    /// mutating any state, so both sides of a transfer can be validated before either is applied.
+    fn can_debit(&self, client: u16, asset: &str, amount: Decimal) -> Result<(), LedgerError> {
+        let account = self
+            .accounts
+            .get(&(client, asset.to_owned()))
+            .cloned()
+            .unwrap_or_default();
+        if account.locked {
+            return Err(LedgerError::FrozenAccount(client));
+        }
+        if amount > account.available {
+            return Err(LedgerError::InsufficientFunds(client));
         }
+        Ok(())
+    }
+
+    /// Checks whether `client`'s `asset` account could accept a credit, i.e. is not frozen,
+    /// without mutating any state.
+    fn can_credit(&self, client: u16, asset: &str) -> Result<(), LedgerError> {
+        if self
+            .accounts
+            .get(&(client, asset.to_owned()))
+            .is_some_and(|account| account.locked)
+        {
+            return Err(LedgerError::FrozenAccount(client));
+        }
+        Ok(())
+    }
+
+    /// Moves `amount` out of `client`'s `asset` account. Callers must have already validated the
+    /// debit with [`Bank::can_debit`].
+    fn debit(&mut self, client: u16, asset: &str, amount: Decimal) {
+        self.accounts
+            .entry((client, asset.to_owned()))
+            .or_default()
+            .available -= amount;
+    }
+
+    /// Moves `amount` into `client`'s `asset` account. Callers must have already validated the
+    /// credit with [`Bank::can_credit`].
+    fn credit(&mut self, client: u16, asset: &str, amount: Decimal) {
+        self.accounts
+            .entry((client, asset.to_owned()))
+            .or_default()
+            .available += amount;
+    }
+
+    /// Iterates this bank's accounts, for merging several `Bank`s' accounts into one sorted
+    /// output (see [`ShardedBank::write`]).
+    fn accounts(&self) -> impl Iterator<Item = (&AccountKey, &Account)> {
+        self.accounts.iter()
     }
 
-    /// Serialize and write the current accounts out to writer
+    /// Verifies that, for every asset, the sum of `available + reserved` across all accounts
+    /// matches the running total of deposits, withdrawals, withdrawal disputes/resolves and
+    /// chargebacks applied to that asset.
+    /// Transfers move funds between clients without changing either side of this check, so any
+    /// mismatch indicates an arithmetic or logic bug rather than legitimate client activity.
+    pub fn audit(&self) -> Result<(), LedgerError> {
+        let mut actual: BTreeMap<&str, Decimal> = BTreeMap::new();
+        for ((_, asset), account) in &self.accounts {
+            *actual.entry(asset.as_str()).or_default() += account.total();
+        }
+
+        for (asset, expected) in &self.total_issuance {
+            let found = actual.get(asset.as_str()).copied().unwrap_or_default();
+            if found != *expected {
+                return Err(LedgerError::IssuanceMismatch(
+                    asset.clone(),
+                    *expected,
+                    found,
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Serialize and write the current accounts out to writer, one row per (client, asset) pair.
     pub fn write<W: io::Write>(&self, writer: W) -> Result<()> {
         // Construct csv writer
         let mut writer = csv::Writer::from_writer(writer);
 
         // Write the accounts
-        for (&client, account) in &self.accounts {
-            writer.serialize(SerializableAccount::from_account(client, &account))?;
+        for ((client, asset), account) in &self.accounts {
+            writer.serialize(SerializableAccount::from_account(*client, asset, account))?;
         }
 
         Ok(())
     }
 }
 
+/// Splits account state across `N` shards, keyed by `client_id % N`, so independent clients'
+/// transactions can be applied concurrently by a worker pool while a given client's own
+/// transactions still land on one shard and are applied in stream order.
+///
+/// [`transaction::Transaction::Transfer`] is the one case that can span two shards. It's handled
+/// by locking both shards involved, in ascending shard-index order (so two concurrent transfers
+/// running in opposite directions can't deadlock on each other), and validating both sides with
+/// [`Bank::can_debit`]/[`Bank::can_credit`] before mutating either with [`Bank::debit`]/
+/// [`Bank::credit`].
+pub struct ShardedBank {
+    shards: Vec<Mutex<Bank>>,
+}
+
+/// A unit of work dispatched to a shard's worker thread: either a transaction to apply, or (for
+/// the destination side of a cross-shard transfer) a marker that blocks the shard until that
+/// transfer has actually been applied. See [`ShardedBank::process_all`].
+enum ShardJob {
+    Apply {
+        original_index: usize,
+        transaction: transaction::Transaction,
+        done: Option<mpsc::Sender<()>>,
+    },
+    AwaitTransfer(mpsc::Receiver<()>),
+}
+
+impl ShardedBank {
+    /// Creates a new bank split across `shard_count` shards.
+    pub fn new(shard_count: usize) -> Self {
+        assert!(shard_count > 0, "a sharded bank needs at least one shard");
+        Self {
+            shards: (0..shard_count).map(|_| Mutex::new(Bank::default())).collect(),
+        }
+    }
+
+    fn shard_of(&self, client: u16) -> usize {
+        client as usize % self.shards.len()
+    }
+
+    /// Applies `transactions` across the shard pool, one worker thread per shard, and returns one
+    /// result per input transaction in the same order the transactions were given. A client's own
+    /// transactions are always dispatched to the same shard and applied in stream order.
+    ///
+    /// A cross-shard [`transaction::Transaction::Transfer`] is dispatched to its source shard as
+    /// usual, but it also leaves an [`ShardJob::AwaitTransfer`] marker on the destination shard's
+    /// queue, in the same relative position the transfer occupies in that client's own stream.
+    /// That marker blocks the destination shard's worker until the transfer has actually been
+    /// applied, so the recipient's later transactions can never run ahead of the credit landing.
+    pub fn process_all(
+        &self,
+        transactions: Vec<transaction::Transaction>,
+    ) -> Vec<Result<(), LedgerError>> {
+        let total = transactions.len();
+
+        let mut dispatchers = Vec::with_capacity(self.shards.len());
+        let mut inboxes = Vec::with_capacity(self.shards.len());
+        for _ in 0..self.shards.len() {
+            let (tx, rx) = mpsc::channel::<ShardJob>();
+            dispatchers.push(tx);
+            inboxes.push(rx);
+        }
+
+        let (result_tx, result_rx) = mpsc::channel::<(usize, Result<(), LedgerError>)>();
+
+        thread::scope(|scope| {
+            for (shard_index, inbox) in inboxes.into_iter().enumerate() {
+                let result_tx = result_tx.clone();
+                scope.spawn(move || {
+                    for job in inbox {
+                        match job {
+                            ShardJob::Apply { original_index, transaction, done } => {
+                                let result = self.apply(shard_index, transaction, done);
+                                result_tx.send((original_index, result)).unwrap();
+                            }
+                            ShardJob::AwaitTransfer(signal) => {
+                                // Block until the shard applying this transfer signals that it's
+                                // done, so this shard can't run the recipient's later items ahead
+                                // of the credit.
+                                let _ = signal.recv();
+                            }
+                        }
+                    }
+                });
+            }
+            drop(result_tx);
+
+            for (original_index, transaction) in transactions.into_iter().enumerate() {
+                let source_shard = self.shard_of(transaction.client_id());
+
+                if let transaction::Transaction::Transfer(data) = &transaction {
+                    let dest_shard = self.shard_of(data.to_client);
+                    if dest_shard != source_shard {
+                        let (done_tx, done_rx) = mpsc::channel();
+                        dispatchers[dest_shard]
+                            .send(ShardJob::AwaitTransfer(done_rx))
+                            .unwrap();
+                        dispatchers[source_shard]
+                            .send(ShardJob::Apply {
+                                original_index,
+                                transaction,
+                                done: Some(done_tx),
+                            })
+                            .unwrap();
+                        continue;
+                    }
+                }
+
+                dispatchers[source_shard]
+                    .send(ShardJob::Apply { original_index, transaction, done: None })
+                    .unwrap();
+            }
+            drop(dispatchers);
+        });
+
+        let mut results: Vec<Option<Result<(), LedgerError>>> = vec![None; total];
+        for (original_index, result) in result_rx.try_iter() {
+            results[original_index] = Some(result);
+        }
+
+        results
+            .into_iter()
+            .map(|result| result.expect("every dispatched transaction returns exactly one result"))
+            .collect()
+    }
+
+    fn apply(
+        &self,
+        shard_index: usize,
+        transaction: transaction::Transaction,
+        done: Option<mpsc::Sender<()>>,
+    ) -> Result<(), LedgerError> {
+        let result = match transaction {
+            transaction::Transaction::Transfer(data) => self.apply_transfer(shard_index, data),
+            other => self.shards[shard_index].lock().unwrap().handle_transaction(other),
+        };
+
+        if let Some(done) = done {
+            // Let a destination shard parked on `ShardJob::AwaitTransfer` know this transfer has
+            // been applied (or definitively failed) and it's safe to move on.
+            let _ = done.send(());
+        }
+
+        result
+    }
+
+    /// Applies a transfer whose source client lives on `source_shard`. If the destination client
+    /// lives on a different shard, both shards are locked (in ascending index order) and the
+    /// transfer is validated across both before either side is mutated.
+    fn apply_transfer(
+        &self,
+        source_shard: usize,
+        data: transaction::TransferData,
+    ) -> Result<(), LedgerError> {
+        let dest_shard = self.shard_of(data.to_client);
+
+        if source_shard == dest_shard {
+            return self.shards[source_shard]
+                .lock()
+                .unwrap()
+                .handle_transaction(transaction::Transaction::Transfer(data));
+        }
+
+        let (low, high) = if source_shard < dest_shard {
+            (source_shard, dest_shard)
+        } else {
+            (dest_shard, source_shard)
+        };
+        let mut low_shard = self.shards[low].lock().unwrap();
+        let mut high_shard = self.shards[high].lock().unwrap();
+        let (source, dest) = if source_shard < dest_shard {
+            (&mut *low_shard, &mut *high_shard)
+        } else {
+            (&mut *high_shard, &mut *low_shard)
+        };
+
+        source.can_debit(data.client, &data.asset, data.amount)?;
+        dest.can_credit(data.to_client, &data.asset)?;
+
+        source.debit(data.client, &data.asset, data.amount);
+        dest.credit(data.to_client, &data.asset, data.amount);
+        Ok(())
+    }
+
+    /// Collapses every shard's accounts and total issuance into a single [`Bank`], as if this
+    /// had never been sharded in the first place, so [`Bank::write`] and [`Bank::audit`] don't
+    /// need a sharded-aware reimplementation.
+    fn merge(&self) -> Bank {
+        let mut merged = Bank::default();
+        for shard in &self.shards {
+            let shard = shard.lock().unwrap();
+            for (key, account) in shard.accounts() {
+                merged.accounts.insert(key.clone(), account.clone());
+            }
+            for (asset, total) in &shard.total_issuance {
+                *merged.total_issuance.entry(asset.clone()).or_default() += *total;
+            }
+        }
+        merged
+    }
+
+    /// Verifies [`Bank::audit`]'s invariant across every shard's accounts and total issuance.
+    pub fn audit(&self) -> Result<(), LedgerError> {
+        self.merge().audit()
+    }
+
+    /// Serializes all shards' accounts merged into a single CSV, sorted by (client, asset) as if
+    /// this were one un-sharded [`Bank`].
+    pub fn write<W: io::Write>(&self, writer: W) -> Result<()> {
+        self.merge().write(writer)
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use crate::transaction::{TransactionAmountData, TransactionData};
+    use crate::transaction::{TransactionAmountData, TransactionData, BASE_ASSET};
     use rust_decimal_macros::dec;
 
     use super::*;
@@ -151,13 +675,19 @@ mod test {
 
     impl TestBank {
         fn deposit(&mut self, client: u16, amount: Decimal) -> u32 {
+            self.deposit_asset(client, BASE_ASSET, amount)
+        }
+
+        fn deposit_asset(&mut self, client: u16, asset: &str, amount: Decimal) -> u32 {
             let tx = self.next_tx;
             self.bank
                 .handle_transaction(transaction::Transaction::Deposit(TransactionAmountData {
                     client,
                     tx,
                     amount,
-                }));
+                    asset: asset.to_owned(),
+                }))
+                .unwrap();
 
             self.next_tx += 1;
 
@@ -168,36 +698,98 @@ mod test {
             let tx = self.next_tx;
             self.bank
                 .handle_transaction(transaction::Transaction::Withdrawal(
-                    TransactionAmountData { client, tx, amount },
-                ));
+                    TransactionAmountData {
+                        client,
+                        tx,
+                        amount,
+                        asset: BASE_ASSET.to_owned(),
+                    },
+                ))
+                .unwrap();
 
             self.next_tx += 1;
 
             tx
         }
 
-        fn dispute(&mut self, client: u16, tx: u32) {
+        fn try_withdraw(&mut self, client: u16, amount: Decimal) -> Result<(), LedgerError> {
+            let tx = self.next_tx;
+            let result = self
+                .bank
+                .handle_transaction(transaction::Transaction::Withdrawal(
+                    TransactionAmountData {
+                        client,
+                        tx,
+                        amount,
+                        asset: BASE_ASSET.to_owned(),
+                    },
+                ));
+
+            self.next_tx += 1;
+
+            result
+        }
+
+        fn dispute(&mut self, client: u16, tx: u32) -> Result<(), LedgerError> {
             self.bank
                 .handle_transaction(transaction::Transaction::Dispute(TransactionData {
                     client,
                     tx,
-                }));
+                }))
         }
 
-        fn resolve(&mut self, client: u16, tx: u32) {
+        fn resolve(&mut self, client: u16, tx: u32) -> Result<(), LedgerError> {
             self.bank
                 .handle_transaction(transaction::Transaction::Resolve(TransactionData {
                     client,
                     tx,
-                }));
+                }))
         }
 
-        fn chargeback(&mut self, client: u16, tx: u32) {
+        fn chargeback(&mut self, client: u16, tx: u32) -> Result<(), LedgerError> {
             self.bank
                 .handle_transaction(transaction::Transaction::Chargeback(TransactionData {
                     client,
                     tx,
-                }));
+                }))
+        }
+
+        fn account(&self, client: u16, asset: &str) -> Option<&Account> {
+            self.bank.accounts.get(&(client, asset.to_owned()))
+        }
+
+        fn transfer(
+            &mut self,
+            client: u16,
+            to_client: u16,
+            amount: Decimal,
+        ) -> Result<(), LedgerError> {
+            self.transfer_asset(client, to_client, BASE_ASSET, amount)
+        }
+
+        fn transfer_asset(
+            &mut self,
+            client: u16,
+            to_client: u16,
+            asset: &str,
+            amount: Decimal,
+        ) -> Result<(), LedgerError> {
+            let tx = self.next_tx;
+            let result = self
+                .bank
+                .handle_transaction(transaction::Transaction::Transfer(
+                    crate::transaction::TransferData {
+                        client,
+                        to_client,
+                        tx,
+                        amount,
+                        asset: asset.to_owned(),
+                    },
+                ));
+
+            self.next_tx += 1;
+
+            result
         }
     }
 
@@ -214,9 +806,9 @@ mod test {
         bank.deposit(CHRIS, dec!(10.0));
         bank.deposit(CHRIS, dec!(10.0));
 
-        assert_eq!(bank.bank.accounts.get(&ALICE).unwrap().available, dec!(10));
-        assert_eq!(bank.bank.accounts.get(&BOB).unwrap().available, dec!(20));
-        assert_eq!(bank.bank.accounts.get(&CHRIS).unwrap().available, dec!(30));
+        assert_eq!(bank.account(ALICE, BASE_ASSET).unwrap().available, dec!(10));
+        assert_eq!(bank.account(BOB, BASE_ASSET).unwrap().available, dec!(20));
+        assert_eq!(bank.account(CHRIS, BASE_ASSET).unwrap().available, dec!(30));
     }
 
     #[test]
@@ -236,9 +828,9 @@ mod test {
         bank.withdraw(BOB, dec!(5));
         bank.withdraw(CHRIS, dec!(5));
 
-        assert_eq!(bank.bank.accounts.get(&ALICE).unwrap().available, dec!(5));
-        assert_eq!(bank.bank.accounts.get(&BOB).unwrap().available, dec!(15));
-        assert_eq!(bank.bank.accounts.get(&CHRIS).unwrap().available, dec!(25));
+        assert_eq!(bank.account(ALICE, BASE_ASSET).unwrap().available, dec!(5));
+        assert_eq!(bank.account(BOB, BASE_ASSET).unwrap().available, dec!(15));
+        assert_eq!(bank.account(CHRIS, BASE_ASSET).unwrap().available, dec!(25));
     }
 
     #[test]
@@ -246,26 +838,50 @@ mod test {
         let mut bank = TestBank::default();
 
         bank.deposit(ALICE, dec!(10.0));
-        bank.withdraw(ALICE, dec!(20.0));
 
         assert_eq!(
-            bank.bank.accounts.get(&ALICE).unwrap().available,
+            bank.try_withdraw(ALICE, dec!(20.0)),
+            Err(LedgerError::InsufficientFunds(ALICE))
+        );
+        assert_eq!(
+            bank.account(ALICE, BASE_ASSET).unwrap().available,
             dec!(10.0)
         );
     }
 
     #[test]
-    fn dispute_withdrawal_no_effect_test() {
+    fn dispute_withdrawal_to_resolve_test() {
         let mut bank = TestBank::default();
 
         bank.deposit(ALICE, dec!(10.0));
         let withdraw_tx = bank.withdraw(ALICE, dec!(5.0));
 
-        let bank_clone = bank.bank.clone();
+        bank.dispute(ALICE, withdraw_tx).unwrap();
+        let account = bank.account(ALICE, BASE_ASSET).unwrap();
+        assert_eq!(account.available, dec!(5.0));
+        assert_eq!(account.reserved(), dec!(5.0));
 
-        bank.dispute(ALICE, withdraw_tx);
+        bank.resolve(ALICE, withdraw_tx).unwrap();
+        let account = bank.account(ALICE, BASE_ASSET).unwrap();
+        assert_eq!(account.available, dec!(5.0));
+        assert_eq!(account.reserved(), dec!(0));
+        assert!(!account.locked);
+    }
 
-        assert_eq!(bank.bank, bank_clone);
+    #[test]
+    fn dispute_withdrawal_to_chargeback_test() {
+        let mut bank = TestBank::default();
+
+        bank.deposit(ALICE, dec!(10.0));
+        let withdraw_tx = bank.withdraw(ALICE, dec!(5.0));
+
+        bank.dispute(ALICE, withdraw_tx).unwrap();
+        bank.chargeback(ALICE, withdraw_tx).unwrap();
+
+        let account = bank.account(ALICE, BASE_ASSET).unwrap();
+        assert_eq!(account.available, dec!(10.0));
+        assert_eq!(account.reserved(), dec!(0));
+        assert!(account.locked);
     }
 
     #[test]
@@ -277,8 +893,10 @@ mod test {
 
         let bank_clone = bank.bank.clone();
 
-        bank.dispute(BOB, deposit_tx);
-
+        assert_eq!(
+            bank.dispute(BOB, deposit_tx),
+            Err(LedgerError::UnknownTx(BOB, deposit_tx))
+        );
         assert_eq!(bank.bank, bank_clone);
     }
 
@@ -289,18 +907,33 @@ mod test {
         bank.deposit(ALICE, dec!(10.0));
         let deposit_tx = bank.deposit(ALICE, dec!(10.0));
 
-        bank.dispute(ALICE, deposit_tx);
-        assert!(bank.bank.disputes.contains(&deposit_tx));
-        assert_eq!(bank.bank.accounts.get(&ALICE).unwrap().held, dec!(10.0));
+        bank.dispute(ALICE, deposit_tx).unwrap();
+        assert_eq!(
+            bank.bank.transactions.get(&deposit_tx).map(|r| r.state),
+            Some(TxState::Disputed)
+        );
+        assert_eq!(bank.account(ALICE, BASE_ASSET).unwrap().reserved(), dec!(10.0));
 
-        bank.resolve(ALICE, deposit_tx);
+        bank.resolve(ALICE, deposit_tx).unwrap();
 
         assert_eq!(
-            bank.bank.accounts.get(&ALICE).unwrap().available,
+            bank.account(ALICE, BASE_ASSET).unwrap().available,
             dec!(20.0)
         );
     }
 
+    #[test]
+    fn resolve_without_dispute_errors_test() {
+        let mut bank = TestBank::default();
+
+        let deposit_tx = bank.deposit(ALICE, dec!(10.0));
+
+        assert_eq!(
+            bank.resolve(ALICE, deposit_tx),
+            Err(LedgerError::NotDisputed(deposit_tx))
+        );
+    }
+
     #[test]
     fn dispute_to_chargeback_test() {
         let mut bank = TestBank::default();
@@ -308,14 +941,491 @@ mod test {
         bank.deposit(ALICE, dec!(10.0));
         let deposit_tx = bank.deposit(ALICE, dec!(10.0));
 
-        bank.dispute(ALICE, deposit_tx);
-        assert!(bank.bank.disputes.contains(&deposit_tx));
-        assert_eq!(bank.bank.accounts.get(&ALICE).unwrap().held, dec!(10.0));
+        bank.dispute(ALICE, deposit_tx).unwrap();
+        assert_eq!(
+            bank.bank.transactions.get(&deposit_tx).map(|r| r.state),
+            Some(TxState::Disputed)
+        );
+        assert_eq!(bank.account(ALICE, BASE_ASSET).unwrap().reserved(), dec!(10.0));
 
-        bank.chargeback(ALICE, deposit_tx);
+        bank.chargeback(ALICE, deposit_tx).unwrap();
 
-        let account = bank.bank.accounts.get(&ALICE).unwrap();
+        let account = bank.account(ALICE, BASE_ASSET).unwrap();
         assert_eq!(account.available, dec!(10.0));
         assert!(account.locked);
     }
+
+    #[test]
+    fn double_dispute_errors_test() {
+        let mut bank = TestBank::default();
+
+        let deposit_tx = bank.deposit(ALICE, dec!(10.0));
+        bank.dispute(ALICE, deposit_tx).unwrap();
+
+        assert_eq!(
+            bank.dispute(ALICE, deposit_tx),
+            Err(LedgerError::AlreadyDisputed(deposit_tx))
+        );
+    }
+
+    #[test]
+    fn frozen_account_rejects_further_transactions_test() {
+        let mut bank = TestBank::default();
+
+        let deposit_tx = bank.deposit(ALICE, dec!(10.0));
+        bank.dispute(ALICE, deposit_tx).unwrap();
+        bank.chargeback(ALICE, deposit_tx).unwrap();
+
+        assert_eq!(
+            bank.try_withdraw(ALICE, dec!(1.0)),
+            Err(LedgerError::FrozenAccount(ALICE))
+        );
+    }
+
+    #[test]
+    fn separate_assets_hold_independent_balances_test() {
+        let mut bank = TestBank::default();
+
+        bank.deposit_asset(ALICE, "USD", dec!(10.0));
+        bank.deposit_asset(ALICE, "BTC", dec!(1.0));
+
+        assert_eq!(bank.account(ALICE, "USD").unwrap().available, dec!(10.0));
+        assert_eq!(bank.account(ALICE, "BTC").unwrap().available, dec!(1.0));
+    }
+
+    #[test]
+    fn dispute_only_affects_the_disputed_asset_test() {
+        let mut bank = TestBank::default();
+
+        bank.deposit_asset(ALICE, "USD", dec!(10.0));
+        let btc_deposit_tx = bank.deposit_asset(ALICE, "BTC", dec!(1.0));
+
+        bank.dispute(ALICE, btc_deposit_tx).unwrap();
+
+        let usd_account = bank.account(ALICE, "USD").unwrap();
+        assert_eq!(usd_account.available, dec!(10.0));
+        assert_eq!(usd_account.reserved(), dec!(0));
+
+        let btc_account = bank.account(ALICE, "BTC").unwrap();
+        assert_eq!(btc_account.available, dec!(0));
+        assert_eq!(btc_account.reserved(), dec!(1.0));
+    }
+
+    #[test]
+    fn write_emits_one_row_per_client_asset_pair_test() {
+        let mut bank = TestBank::default();
+
+        bank.deposit_asset(ALICE, "USD", dec!(10.0));
+        bank.deposit_asset(ALICE, "BTC", dec!(1.0));
+
+        let mut buffer = Vec::new();
+        bank.bank.write(&mut buffer).unwrap();
+
+        let output = String::from_utf8(buffer).unwrap();
+        assert_eq!(
+            output,
+            "client,asset,available,held,total,locked\n\
+             1,BTC,1,0,1,false\n\
+             1,USD,10,0,10,false\n"
+        );
+    }
+
+    #[test]
+    fn transfer_moves_available_between_clients_test() {
+        let mut bank = TestBank::default();
+
+        bank.deposit(ALICE, dec!(10.0));
+        bank.transfer(ALICE, BOB, dec!(4.0)).unwrap();
+
+        assert_eq!(bank.account(ALICE, BASE_ASSET).unwrap().available, dec!(6.0));
+        assert_eq!(bank.account(BOB, BASE_ASSET).unwrap().available, dec!(4.0));
+    }
+
+    #[test]
+    fn transfer_over_available_errors_test() {
+        let mut bank = TestBank::default();
+
+        bank.deposit(ALICE, dec!(10.0));
+
+        assert_eq!(
+            bank.transfer(ALICE, BOB, dec!(20.0)),
+            Err(LedgerError::InsufficientFunds(ALICE))
+        );
+        assert_eq!(bank.account(ALICE, BASE_ASSET).unwrap().available, dec!(10.0));
+        assert!(bank.account(BOB, BASE_ASSET).is_none());
+    }
+
+    #[test]
+    fn transfer_from_frozen_account_errors_test() {
+        let mut bank = TestBank::default();
+
+        let deposit_tx = bank.deposit(ALICE, dec!(10.0));
+        bank.dispute(ALICE, deposit_tx).unwrap();
+        bank.chargeback(ALICE, deposit_tx).unwrap();
+
+        assert_eq!(
+            bank.transfer(ALICE, BOB, dec!(1.0)),
+            Err(LedgerError::FrozenAccount(ALICE))
+        );
+    }
+
+    #[test]
+    fn transfer_to_frozen_account_errors_test() {
+        let mut bank = TestBank::default();
+
+        bank.deposit(ALICE, dec!(10.0));
+        let bob_deposit_tx = bank.deposit(BOB, dec!(10.0));
+        bank.dispute(BOB, bob_deposit_tx).unwrap();
+        bank.chargeback(BOB, bob_deposit_tx).unwrap();
+
+        assert_eq!(
+            bank.transfer(ALICE, BOB, dec!(1.0)),
+            Err(LedgerError::FrozenAccount(BOB))
+        );
+        assert_eq!(bank.account(ALICE, BASE_ASSET).unwrap().available, dec!(10.0));
+    }
+
+    #[test]
+    fn audit_passes_after_deposits_withdrawals_transfers_test() {
+        let mut bank = TestBank::default();
+
+        bank.deposit(ALICE, dec!(10.0));
+        bank.deposit(BOB, dec!(5.0));
+        bank.withdraw(ALICE, dec!(2.0));
+        bank.transfer(ALICE, BOB, dec!(3.0)).unwrap();
+
+        assert_eq!(bank.bank.audit(), Ok(()));
+    }
+
+    #[test]
+    fn audit_passes_after_deposit_and_withdrawal_chargebacks_test() {
+        let mut bank = TestBank::default();
+
+        let deposit_tx = bank.deposit(ALICE, dec!(10.0));
+        bank.dispute(ALICE, deposit_tx).unwrap();
+        bank.chargeback(ALICE, deposit_tx).unwrap();
+        assert_eq!(bank.bank.audit(), Ok(()));
+
+        let mut bank = TestBank::default();
+        bank.deposit(BOB, dec!(10.0));
+        let withdraw_tx = bank.withdraw(BOB, dec!(4.0));
+        bank.dispute(BOB, withdraw_tx).unwrap();
+        bank.chargeback(BOB, withdraw_tx).unwrap();
+        assert_eq!(bank.bank.audit(), Ok(()));
+    }
+
+    #[test]
+    fn audit_passes_while_withdrawal_dispute_is_open_test() {
+        let mut bank = TestBank::default();
+
+        bank.deposit(ALICE, dec!(10.0));
+        let withdraw_tx = bank.withdraw(ALICE, dec!(4.0));
+        bank.dispute(ALICE, withdraw_tx).unwrap();
+
+        assert_eq!(bank.bank.audit(), Ok(()));
+    }
+
+    #[test]
+    fn audit_passes_after_withdrawal_dispute_resolves_test() {
+        let mut bank = TestBank::default();
+
+        bank.deposit(ALICE, dec!(10.0));
+        let withdraw_tx = bank.withdraw(ALICE, dec!(4.0));
+        bank.dispute(ALICE, withdraw_tx).unwrap();
+        bank.resolve(ALICE, withdraw_tx).unwrap();
+
+        assert_eq!(bank.bank.audit(), Ok(()));
+    }
+
+    #[test]
+    fn audit_detects_tampered_balance_test() {
+        let mut bank = TestBank::default();
+
+        bank.deposit(ALICE, dec!(10.0));
+        bank.bank
+            .accounts
+            .get_mut(&(ALICE, BASE_ASSET.to_owned()))
+            .unwrap()
+            .available += dec!(1.0);
+
+        assert_eq!(
+            bank.bank.audit(),
+            Err(LedgerError::IssuanceMismatch(
+                BASE_ASSET.to_owned(),
+                dec!(10.0),
+                dec!(11.0)
+            ))
+        );
+    }
+
+    #[test]
+    fn reserve_moves_funds_out_of_available_test() {
+        let mut account = Account {
+            available: dec!(10.0),
+            ..Default::default()
+        };
+
+        account.reserve(ALICE, 1, dec!(4.0)).unwrap();
+
+        assert_eq!(account.available, dec!(6.0));
+        assert_eq!(account.reserved(), dec!(4.0));
+        assert_eq!(account.total(), dec!(10.0));
+    }
+
+    #[test]
+    fn reserve_cannot_push_available_below_zero_test() {
+        let mut account = Account {
+            available: dec!(10.0),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            account.reserve(ALICE, 1, dec!(20.0)),
+            Err(LedgerError::InsufficientFunds(ALICE))
+        );
+        assert_eq!(account.available, dec!(10.0));
+    }
+
+    #[test]
+    fn multiple_reserves_overlay_the_same_available_balance_test() {
+        let mut account = Account {
+            available: dec!(10.0),
+            ..Default::default()
+        };
+
+        account.reserve(ALICE, 1, dec!(4.0)).unwrap();
+        account.reserve(ALICE, 2, dec!(4.0)).unwrap();
+
+        assert_eq!(account.available, dec!(2.0));
+        assert_eq!(account.reserved(), dec!(8.0));
+        assert_eq!(
+            account.reserve(ALICE, 3, dec!(4.0)),
+            Err(LedgerError::InsufficientFunds(ALICE))
+        );
+    }
+
+    #[test]
+    fn unreserve_returns_funds_to_available_test() {
+        let mut account = Account {
+            available: dec!(10.0),
+            ..Default::default()
+        };
+
+        account.reserve(ALICE, 1, dec!(4.0)).unwrap();
+        account.unreserve(ALICE, 1, dec!(4.0)).unwrap();
+
+        assert_eq!(account.available, dec!(10.0));
+        assert_eq!(account.reserved(), dec!(0));
+    }
+
+    #[test]
+    fn slash_reserved_drops_funds_without_crediting_available_test() {
+        let mut account = Account {
+            available: dec!(10.0),
+            ..Default::default()
+        };
+
+        account.reserve(ALICE, 1, dec!(4.0)).unwrap();
+        account.slash_reserved(ALICE, 1, dec!(4.0)).unwrap();
+
+        assert_eq!(account.available, dec!(6.0));
+        assert_eq!(account.reserved(), dec!(0));
+        assert_eq!(account.total(), dec!(6.0));
+    }
+
+    #[test]
+    fn unreserve_unknown_id_errors_test() {
+        let mut account = Account::default();
+
+        assert_eq!(
+            account.unreserve(ALICE, 1, dec!(1.0)),
+            Err(LedgerError::UnknownReserve(ALICE, 1))
+        );
+    }
+
+    #[test]
+    fn unreserve_more_than_reserved_errors_test() {
+        let mut account = Account {
+            available: dec!(10.0),
+            ..Default::default()
+        };
+        account.reserve(ALICE, 1, dec!(4.0)).unwrap();
+
+        assert_eq!(
+            account.unreserve(ALICE, 1, dec!(5.0)),
+            Err(LedgerError::InsufficientReserve(ALICE, 1, dec!(5.0)))
+        );
+    }
+
+    fn deposit(client: u16, tx: u32, amount: Decimal) -> transaction::Transaction {
+        transaction::Transaction::Deposit(TransactionAmountData {
+            client,
+            tx,
+            amount,
+            asset: BASE_ASSET.to_owned(),
+        })
+    }
+
+    fn withdrawal(client: u16, tx: u32, amount: Decimal) -> transaction::Transaction {
+        transaction::Transaction::Withdrawal(TransactionAmountData {
+            client,
+            tx,
+            amount,
+            asset: BASE_ASSET.to_owned(),
+        })
+    }
+
+    fn transfer(client: u16, to_client: u16, tx: u32, amount: Decimal) -> transaction::Transaction {
+        transaction::Transaction::Transfer(crate::transaction::TransferData {
+            client,
+            to_client,
+            tx,
+            amount,
+            asset: BASE_ASSET.to_owned(),
+        })
+    }
+
+    #[test]
+    fn sharded_bank_applies_deposits_and_withdrawals_test() {
+        let bank = ShardedBank::new(4);
+
+        let results = bank.process_all(vec![
+            deposit(ALICE, 1, dec!(10.0)),
+            deposit(BOB, 2, dec!(5.0)),
+            withdrawal(ALICE, 3, dec!(4.0)),
+        ]);
+
+        assert!(results.iter().all(|result| result.is_ok()));
+
+        let mut buffer = Vec::new();
+        bank.write(&mut buffer).unwrap();
+        let output = String::from_utf8(buffer).unwrap();
+        assert_eq!(
+            output,
+            "client,asset,available,held,total,locked\n\
+             1,USD,6,0,6,false\n\
+             2,USD,5,0,5,false\n"
+        );
+    }
+
+    #[test]
+    fn sharded_bank_preserves_per_client_order_test() {
+        let bank = ShardedBank::new(2);
+
+        // An overdraft followed by a deposit would succeed if reordered, so this only passes if
+        // Alice's own transactions are actually applied in stream order.
+        let results = bank.process_all(vec![
+            deposit(ALICE, 1, dec!(5.0)),
+            withdrawal(ALICE, 2, dec!(10.0)),
+            deposit(ALICE, 3, dec!(20.0)),
+        ]);
+
+        assert_eq!(results[0], Ok(()));
+        assert_eq!(results[1], Err(LedgerError::InsufficientFunds(ALICE)));
+        assert_eq!(results[2], Ok(()));
+
+        let mut buffer = Vec::new();
+        bank.write(&mut buffer).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "client,asset,available,held,total,locked\n\
+             1,USD,25,0,25,false\n"
+        );
+    }
+
+    #[test]
+    fn sharded_bank_same_shard_transfer_test() {
+        let bank = ShardedBank::new(4);
+
+        let results = bank.process_all(vec![
+            deposit(ALICE, 1, dec!(10.0)),
+            transfer(ALICE, 5, 2, dec!(4.0)),
+        ]);
+
+        assert!(results.iter().all(|result| result.is_ok()));
+        assert_eq!(bank.shard_of(ALICE), bank.shard_of(5));
+
+        let mut buffer = Vec::new();
+        bank.write(&mut buffer).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "client,asset,available,held,total,locked\n\
+             1,USD,6,0,6,false\n\
+             5,USD,4,0,4,false\n"
+        );
+    }
+
+    #[test]
+    fn sharded_bank_cross_shard_transfer_test() {
+        let bank = ShardedBank::new(4);
+
+        let results = bank.process_all(vec![
+            deposit(ALICE, 1, dec!(10.0)),
+            transfer(ALICE, BOB, 2, dec!(4.0)),
+        ]);
+
+        assert!(results.iter().all(|result| result.is_ok()));
+        assert_ne!(bank.shard_of(ALICE), bank.shard_of(BOB));
+
+        let mut buffer = Vec::new();
+        bank.write(&mut buffer).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "client,asset,available,held,total,locked\n\
+             1,USD,6,0,6,false\n\
+             2,USD,4,0,4,false\n"
+        );
+    }
+
+    #[test]
+    fn sharded_bank_cross_shard_transfer_over_available_errors_test() {
+        let bank = ShardedBank::new(4);
+
+        let results = bank.process_all(vec![
+            deposit(ALICE, 1, dec!(10.0)),
+            transfer(ALICE, BOB, 2, dec!(20.0)),
+        ]);
+
+        assert_eq!(results[0], Ok(()));
+        assert_eq!(results[1], Err(LedgerError::InsufficientFunds(ALICE)));
+
+        let mut buffer = Vec::new();
+        bank.write(&mut buffer).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "client,asset,available,held,total,locked\n\
+             1,USD,10,0,10,false\n"
+        );
+    }
+
+    #[test]
+    fn sharded_bank_audit_passes_after_cross_shard_transfer_test() {
+        let bank = ShardedBank::new(4);
+
+        let results = bank.process_all(vec![
+            deposit(ALICE, 1, dec!(10.0)),
+            transfer(ALICE, BOB, 2, dec!(4.0)),
+        ]);
+
+        assert!(results.iter().all(|result| result.is_ok()));
+        assert_eq!(bank.audit(), Ok(()));
+    }
+
+    #[test]
+    fn sharded_bank_recipient_sees_transfer_before_its_own_next_transaction_test() {
+        // Bob's withdrawal only has funds if Alice's transfer into him, dispatched via Alice's
+        // shard, is actually applied before Bob's own shard runs his withdrawal. Repeated to give
+        // the worker threads a real chance to race if the ordering barrier is missing.
+        for _ in 0..200 {
+            let bank = ShardedBank::new(4);
+
+            let results = bank.process_all(vec![
+                deposit(ALICE, 1, dec!(10.0)),
+                transfer(ALICE, BOB, 2, dec!(10.0)),
+                withdrawal(BOB, 3, dec!(10.0)),
+            ]);
+
+            assert!(results.iter().all(|result| result.is_ok()), "{results:?}");
+        }
+    }
 }
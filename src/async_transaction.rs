@@ -0,0 +1,136 @@
+//! Async counterpart to [`TransactionIterator`](crate::transaction::TransactionIterator), gated
+//! behind the `async` feature so the default sync build pulls in neither tokio nor csv-async.
+
+use async_stream::try_stream;
+use csv_async::AsyncReaderBuilder;
+use futures_core::Stream;
+use tokio::io::AsyncRead;
+
+use crate::transaction::{self, Transaction};
+use crate::{Error, Result};
+
+// As with `impl From<csv::Error> for Error` in transaction.rs, unpack IO errors into Error::IO
+// rather than burying them in Error::CSVAsync.
+impl From<csv_async::Error> for Error {
+    fn from(csv_error: csv_async::Error) -> Self {
+        if csv_error.is_io_error() {
+            match csv_error.into_kind() {
+                csv_async::ErrorKind::Io(io_error) => io_error.into(),
+                _ => unreachable!(),
+            }
+        } else {
+            Self::CSVAsync(csv_error)
+        }
+    }
+}
+
+/// Streams [`Transaction`]s out of an [`tokio::io::AsyncRead`] source without blocking, applying
+/// the same header validation, whitespace trimming and 4-decimal capping as the sync
+/// `TransactionIterator`.
+pub fn transaction_stream<R>(reader: R) -> impl Stream<Item = Result<Transaction>>
+where
+    R: AsyncRead + Unpin + Send,
+{
+    try_stream! {
+        let mut reader = AsyncReaderBuilder::new()
+            .trim(csv_async::Trim::All)
+            .flexible(true)
+            .has_headers(true)
+            .create_reader(reader);
+
+        let mut csv_header: csv::StringRecord = reader.headers().await?.iter().collect();
+        csv_header.trim();
+        if !transaction::is_expected_header(&csv_header) {
+            Err(Error::InvalidHeader(csv_header.clone()))?;
+        }
+
+        let mut record = csv_async::StringRecord::new();
+        while reader.read_record(&mut record).await? {
+            let mut record: csv::StringRecord = record.iter().collect();
+            record.trim();
+            yield Transaction::from_record(&record, &csv_header)?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use futures_util::StreamExt;
+    use rust_decimal_macros::dec;
+    use transaction::{TransactionAmountData, TransactionData, BASE_ASSET};
+
+    #[tokio::test]
+    async fn stream_matches_sync_iterator() {
+        let input = "type,client,tx,amount\n\
+                      deposit,1,1,1.0\n\
+                      withdrawal,1,2,0.5\n\
+                      dispute,1,1\n";
+
+        let transactions: Vec<_> = transaction_stream(input.as_bytes())
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .map(|t| t.unwrap())
+            .collect();
+
+        let sync_transactions: Vec<_> =
+            transaction::TransactionIterator::from_reader(input.as_bytes())
+                .unwrap()
+                .map(|t| t.unwrap())
+                .collect();
+        assert_eq!(transactions, sync_transactions);
+
+        assert_eq!(
+            transactions,
+            [
+                Transaction::Deposit(TransactionAmountData {
+                    client: 1,
+                    tx: 1,
+                    amount: dec!(1.0),
+                    asset: BASE_ASSET.to_owned()
+                }),
+                Transaction::Withdrawal(TransactionAmountData {
+                    client: 1,
+                    tx: 2,
+                    amount: dec!(0.5),
+                    asset: BASE_ASSET.to_owned()
+                }),
+                Transaction::Dispute(TransactionData { client: 1, tx: 1 }),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn stream_accepts_asset_and_transfer_headers() {
+        let input = "type,client,tx,amount,asset,to_client\n\
+                      deposit,1,1,1.0,BTC,\n\
+                      transfer,1,2,0.5,BTC,2\n";
+
+        let transactions: Vec<_> = transaction_stream(input.as_bytes())
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .map(|t| t.unwrap())
+            .collect();
+
+        assert_eq!(
+            transactions,
+            [
+                Transaction::Deposit(TransactionAmountData {
+                    client: 1,
+                    tx: 1,
+                    amount: dec!(1.0),
+                    asset: "BTC".to_owned()
+                }),
+                Transaction::Transfer(transaction::TransferData {
+                    client: 1,
+                    to_client: 2,
+                    tx: 2,
+                    amount: dec!(0.5),
+                    asset: "BTC".to_owned()
+                }),
+            ]
+        );
+    }
+}
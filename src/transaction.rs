@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::io;
 
 use super::{Error, Result};
@@ -29,11 +30,94 @@ pub struct TransactionData {
     pub tx: u32,
 }
 
+/// The asset a deposit/withdrawal is denominated in. Input rows that omit the `asset` column
+/// entirely are treated as this base asset, so single-currency CSVs keep working unchanged.
+pub const BASE_ASSET: &str = "USD";
+
+fn default_asset() -> String {
+    BASE_ASSET.to_owned()
+}
+
 #[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
 pub struct TransactionAmountData {
     pub client: u16,
     pub tx: u32,
     pub amount: Decimal,
+    pub asset: String,
+}
+
+/// Intermediate deserialization target for deposit/withdrawal rows, used so a missing amount
+/// cell can be distinguished from a present-but-zero one before [`TransactionAmountData`] is
+/// built.
+#[derive(Clone, Debug, Deserialize)]
+struct RawTransactionAmountData {
+    client: u16,
+    tx: u32,
+    amount: Option<Decimal>,
+    #[serde(default = "default_asset")]
+    asset: String,
+}
+
+impl RawTransactionAmountData {
+    fn into_amount_data(self) -> Result<TransactionAmountData> {
+        let amount = self
+            .amount
+            .ok_or(Error::MissingAmount { tx: self.tx })?;
+        if amount <= Decimal::ZERO {
+            return Err(Error::InvalidAmount { tx: self.tx });
+        }
+
+        Ok(TransactionAmountData {
+            client: self.client,
+            tx: self.tx,
+            amount,
+            asset: self.asset,
+        })
+    }
+}
+
+/// A transfer of funds from `client` to `to_client`, denominated in `asset`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+pub struct TransferData {
+    pub client: u16,
+    pub to_client: u16,
+    pub tx: u32,
+    pub amount: Decimal,
+    pub asset: String,
+}
+
+/// Intermediate deserialization target for transfer rows, used so a missing amount or
+/// destination client can be reported with a dedicated error rather than defaulting to zero/0.
+#[derive(Clone, Debug, Deserialize)]
+struct RawTransferData {
+    client: u16,
+    to_client: Option<u16>,
+    tx: u32,
+    amount: Option<Decimal>,
+    #[serde(default = "default_asset")]
+    asset: String,
+}
+
+impl RawTransferData {
+    fn into_transfer_data(self) -> Result<TransferData> {
+        let amount = self
+            .amount
+            .ok_or(Error::MissingAmount { tx: self.tx })?;
+        if amount <= Decimal::ZERO {
+            return Err(Error::InvalidAmount { tx: self.tx });
+        }
+        let to_client = self
+            .to_client
+            .ok_or(Error::MissingDestinationClient { tx: self.tx })?;
+
+        Ok(TransferData {
+            client: self.client,
+            to_client,
+            tx: self.tx,
+            amount,
+            asset: self.asset,
+        })
+    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -43,21 +127,44 @@ pub enum Transaction {
     Dispute(TransactionData),
     Resolve(TransactionData),
     Chargeback(TransactionData),
+    Transfer(TransferData),
 }
 
 impl Transaction {
-    fn from_record(record: &csv::StringRecord, header: &csv::StringRecord) -> Result<Self> {
+    /// Parses a single CSV record into a [`Transaction`], given the (already validated) header.
+    ///
+    /// `pub(crate)` so the async streaming parser can reuse the exact same parsing and
+    /// precision-capping logic as [`TransactionIterator`].
+    pub(crate) fn from_record(record: &csv::StringRecord, header: &csv::StringRecord) -> Result<Self> {
         // Get header type
         let record_type = record
             .get(0)
             .ok_or_else(|| Error::InvalidRecordType("".to_owned()))?;
 
+        // `record.deserialize(Some(header))` walks every key in `header`, regardless of which
+        // fields the target struct actually declares, so a dispute/resolve/chargeback row that
+        // simply omits the trailing `amount`/`asset` cells (rather than leaving them blank) would
+        // otherwise fail with `UnexpectedEndOfRow`. Pad it out to the header's width first so a
+        // short record behaves the same as one with blank trailing cells.
+        let record = pad_to_header_len(record, header);
+        let record = record.as_ref();
+
         let mut transaction = match record_type {
-            "deposit" => Transaction::Deposit(record.deserialize(Some(header))?),
-            "withdrawal" => Transaction::Withdrawal(record.deserialize(Some(header))?),
+            "deposit" => {
+                let raw: RawTransactionAmountData = record.deserialize(Some(header))?;
+                Transaction::Deposit(raw.into_amount_data()?)
+            }
+            "withdrawal" => {
+                let raw: RawTransactionAmountData = record.deserialize(Some(header))?;
+                Transaction::Withdrawal(raw.into_amount_data()?)
+            }
             "dispute" => Transaction::Dispute(record.deserialize(Some(header))?),
             "resolve" => Transaction::Resolve(record.deserialize(Some(header))?),
             "chargeback" => Transaction::Chargeback(record.deserialize(Some(header))?),
+            "transfer" => {
+                let raw: RawTransferData = record.deserialize(Some(header))?;
+                Transaction::Transfer(raw.into_transfer_data()?)
+            }
             other => return Err(Error::InvalidRecordType(other.to_owned())),
         };
 
@@ -73,6 +180,7 @@ impl Transaction {
     pub fn amount_mut(&mut self) -> Option<&mut Decimal> {
         match self {
             Self::Deposit(data) | Self::Withdrawal(data) => Some(&mut data.amount),
+            Self::Transfer(data) => Some(&mut data.amount),
             _ => None,
         }
     }
@@ -84,10 +192,30 @@ impl Transaction {
             Transaction::Dispute(data)
             | Transaction::Resolve(data)
             | Transaction::Chargeback(data) => data.client,
+            Transaction::Transfer(data) => data.client,
         }
     }
 }
 
+/// Pads `record` out with empty trailing fields so it's at least as wide as `header`, without
+/// touching records that are already long enough. Needed because `csv::StringRecord::deserialize`
+/// reads from `header` rather than from the target struct's own field list, so it errors on a
+/// short record even when every field the target actually declares is present.
+fn pad_to_header_len<'a>(
+    record: &'a csv::StringRecord,
+    header: &csv::StringRecord,
+) -> Cow<'a, csv::StringRecord> {
+    if record.len() >= header.len() {
+        return Cow::Borrowed(record);
+    }
+
+    let mut padded = record.clone();
+    for _ in record.len()..header.len() {
+        padded.push_field("");
+    }
+    Cow::Owned(padded)
+}
+
 pub struct TransactionIterator<R: io::Read> {
     // Inner csv reader
     reader: csv::Reader<R>,
@@ -120,18 +248,50 @@ impl<R: io::Read> Iterator for TransactionIterator<R> {
     }
 }
 
+/// The header shapes we accept, shared with the async streaming parser. The `asset` column is
+/// optional for backward compatibility with single-currency inputs, and `to_client` is only
+/// needed once a stream contains `transfer` rows.
+pub(crate) const EXPECTED_HEADER: &[&str] = &["type", "client", "tx", "amount"];
+pub(crate) const EXPECTED_HEADER_WITH_ASSET: &[&str] = &["type", "client", "tx", "amount", "asset"];
+pub(crate) const EXPECTED_HEADER_WITH_TRANSFER: &[&str] =
+    &["type", "client", "tx", "amount", "asset", "to_client"];
+
+/// Returns whether `header` matches one of the accepted header shapes.
+pub(crate) fn is_expected_header(header: &csv::StringRecord) -> bool {
+    header == EXPECTED_HEADER
+        || header == EXPECTED_HEADER_WITH_ASSET
+        || header == EXPECTED_HEADER_WITH_TRANSFER
+}
+
 impl<R: io::Read> TransactionIterator<R> {
-    const EXPECTED_HEADER: &'static [&'static str] = &["type", "client", "tx", "amount"];
     /// Takes a reader R for csv formatted data and returns a TransactionIterator.
     ///
-    /// Returns an error if the csv header is not parsed to:
-    /// type, client, tx, amount
+    /// Returns an error if the csv header is not parsed to `type, client, tx, amount`, optionally
+    /// followed by an `asset` column, and then an additional `to_client` column if the stream
+    /// contains `transfer` rows.
+    ///
+    /// Records are read with a [`csv::ReaderBuilder`] configured to trim whitespace and allow a
+    /// variable number of fields per record, since dispute/resolve/chargeback rows legitimately
+    /// omit the trailing `amount` column. Use [`Self::from_reader_with_builder`] to customize
+    /// this further, e.g. to change the delimiter.
     pub fn from_reader(reader: R) -> Result<Self> {
-        let mut reader = csv::Reader::from_reader(reader);
+        let mut builder = csv::ReaderBuilder::new();
+        builder
+            .trim(csv::Trim::All)
+            .flexible(true)
+            .has_headers(true);
+
+        Self::from_reader_with_builder(reader, builder)
+    }
+
+    /// Like [`Self::from_reader`], but lets the caller supply their own [`csv::ReaderBuilder`]
+    /// (e.g. to override the delimiter or flexibility).
+    pub fn from_reader_with_builder(reader: R, builder: csv::ReaderBuilder) -> Result<Self> {
+        let mut reader = builder.from_reader(reader);
 
         let mut csv_header = reader.headers()?.to_owned();
         csv_header.trim();
-        if &csv_header == Self::EXPECTED_HEADER {
+        if is_expected_header(&csv_header) {
             Ok(Self {
                 reader,
                 csv_header,
@@ -168,26 +328,31 @@ mod test {
                 client: 1,
                 tx: 1,
                 amount: dec!(1.0),
+                asset: BASE_ASSET.to_owned(),
             }),
             Transaction::Deposit(TransactionAmountData {
                 client: 2,
                 tx: 2,
                 amount: dec!(2.0),
+                asset: BASE_ASSET.to_owned(),
             }),
             Transaction::Deposit(TransactionAmountData {
                 client: 1,
                 tx: 3,
                 amount: dec!(2.0),
+                asset: BASE_ASSET.to_owned(),
             }),
             Transaction::Withdrawal(TransactionAmountData {
                 client: 1,
                 tx: 4,
                 amount: dec!(1.5),
+                asset: BASE_ASSET.to_owned(),
             }),
             Transaction::Withdrawal(TransactionAmountData {
                 client: 2,
                 tx: 5,
                 amount: dec!(3.0),
+                asset: BASE_ASSET.to_owned(),
             }),
             Transaction::Dispute(TransactionData { client: 1, tx: 1 }),
             Transaction::Resolve(TransactionData { client: 2, tx: 2 }),
@@ -253,4 +418,147 @@ mod test {
             other => panic!("Unexpected transaction type: {:#?}", other),
         }
     }
+
+    /// Test that dispute/resolve/chargeback rows parse when they omit the trailing amount
+    /// column entirely, rather than just leaving it blank.
+    #[test]
+    fn missing_trailing_amount_column_test() {
+        let input = "type,client,tx,amount\ndispute,1,1\nresolve,2,2\nchargeback,3,3\n";
+
+        let transaction_iter =
+            TransactionIterator::from_reader(std::io::Cursor::new(input)).unwrap();
+        let transactions: Vec<_> = transaction_iter.map(|t| t.unwrap()).collect();
+
+        assert_eq!(
+            transactions,
+            [
+                Transaction::Dispute(TransactionData { client: 1, tx: 1 }),
+                Transaction::Resolve(TransactionData { client: 2, tx: 2 }),
+                Transaction::Chargeback(TransactionData { client: 3, tx: 3 }),
+            ]
+        );
+    }
+
+    /// Test that `from_reader_with_builder` allows overriding the reader configuration.
+    #[test]
+    fn from_reader_with_builder_allows_custom_delimiter_test() {
+        let input = "type;client;tx;amount\ndeposit;1;1;1.0\n";
+
+        let mut builder = csv::ReaderBuilder::new();
+        builder.delimiter(b';').trim(csv::Trim::All);
+
+        let mut transaction_iter =
+            TransactionIterator::from_reader_with_builder(std::io::Cursor::new(input), builder)
+                .unwrap();
+
+        assert_eq!(
+            transaction_iter.next().unwrap().unwrap(),
+            Transaction::Deposit(TransactionAmountData {
+                client: 1,
+                tx: 1,
+                amount: dec!(1.0),
+                asset: BASE_ASSET.to_owned()
+            })
+        );
+    }
+
+    /// Test that a deposit with an empty amount cell is rejected instead of silently becoming
+    /// `dec!(0)`.
+    #[test]
+    fn empty_amount_deposit_errors_test() {
+        let input = "type,client,tx,amount\ndeposit,1,1,\n";
+
+        let mut transaction_iter =
+            TransactionIterator::from_reader(std::io::Cursor::new(input)).unwrap();
+
+        assert!(matches!(
+            transaction_iter.next(),
+            Some(Err(Error::MissingAmount { tx: 1 }))
+        ));
+    }
+
+    /// Test that a deposit/withdrawal with a negative or zero amount is rejected.
+    #[test]
+    fn non_positive_amount_errors_test() {
+        let input =
+            "type,client,tx,amount\ndeposit,1,1,-1.0\nwithdrawal,1,2,0\n";
+
+        let mut transaction_iter =
+            TransactionIterator::from_reader(std::io::Cursor::new(input)).unwrap();
+
+        assert!(matches!(
+            transaction_iter.next(),
+            Some(Err(Error::InvalidAmount { tx: 1 }))
+        ));
+        assert!(matches!(
+            transaction_iter.next(),
+            Some(Err(Error::InvalidAmount { tx: 2 }))
+        ));
+    }
+
+    /// Test that a deposit without an `asset` column defaults to the base asset.
+    #[test]
+    fn missing_asset_column_defaults_to_base_asset_test() {
+        let input = "type,client,tx,amount\ndeposit,1,1,1.0\n";
+
+        let mut transaction_iter =
+            TransactionIterator::from_reader(std::io::Cursor::new(input)).unwrap();
+
+        match transaction_iter.next() {
+            Some(Ok(Transaction::Deposit(TransactionAmountData { asset, .. }))) => {
+                assert_eq!(asset, BASE_ASSET)
+            }
+            other => panic!("Unexpected transaction type: {:#?}", other),
+        }
+    }
+
+    /// Test that a deposit with an explicit `asset` column is parsed into that asset.
+    #[test]
+    fn explicit_asset_column_is_parsed_test() {
+        let input = "type,client,tx,amount,asset\ndeposit,1,1,1.0,BTC\n";
+
+        let mut transaction_iter =
+            TransactionIterator::from_reader(std::io::Cursor::new(input)).unwrap();
+
+        match transaction_iter.next() {
+            Some(Ok(Transaction::Deposit(TransactionAmountData { asset, .. }))) => {
+                assert_eq!(asset, "BTC")
+            }
+            other => panic!("Unexpected transaction type: {:#?}", other),
+        }
+    }
+
+    /// Test that a transfer row is parsed into a `Transaction::Transfer`.
+    #[test]
+    fn transfer_is_parsed_test() {
+        let input = "type,client,tx,amount,asset,to_client\ntransfer,1,1,1.0,USD,2\n";
+
+        let mut transaction_iter =
+            TransactionIterator::from_reader(std::io::Cursor::new(input)).unwrap();
+
+        assert_eq!(
+            transaction_iter.next().unwrap().unwrap(),
+            Transaction::Transfer(TransferData {
+                client: 1,
+                to_client: 2,
+                tx: 1,
+                amount: dec!(1.0),
+                asset: "USD".to_owned(),
+            })
+        );
+    }
+
+    /// Test that a transfer row with no destination client is rejected.
+    #[test]
+    fn transfer_missing_to_client_errors_test() {
+        let input = "type,client,tx,amount,asset,to_client\ntransfer,1,1,1.0,USD,\n";
+
+        let mut transaction_iter =
+            TransactionIterator::from_reader(std::io::Cursor::new(input)).unwrap();
+
+        assert!(matches!(
+            transaction_iter.next(),
+            Some(Err(Error::MissingDestinationClient { tx: 1 }))
+        ));
+    }
 }